@@ -1,15 +1,24 @@
-use std::{collections::HashMap};
+use std::{cell::RefCell, collections::HashMap, path::PathBuf};
 
 #[derive(Debug)]
 pub enum Action<'a> {
     Unknown(String),
     Incorrect(String, &'a Verb<'a>),
-    BadParameter(String, &'a Command),
-    Run(Vec<ParameterValue<'a>>),
-    Help(Vec<ParameterValue<'a>>),
+    BadParameter(String, ValueKind, &'a Command<'a>),
+    MissingParameter(String, &'a Command<'a>),
+    ConflictingParameters(String, String, &'a Command<'a>),
+    Run(String, Vec<ParameterValue<'a>>),
+    Help(String, HelpTarget<'a>),
     Exit,
 }
 
+#[derive(Debug)]
+pub enum HelpTarget<'a> {
+    Root(&'a Parser<'a>),
+    Verb(&'a Verb<'a>),
+    Command(&'a Command<'a>),
+}
+
 pub trait Informational {
     fn get_help(&self) -> &Manual;
 }
@@ -27,12 +36,148 @@ impl<'a> Manual<'a> {
             detailed_help,
         }
     }
+
+    pub fn short_description(&self) -> &str {
+        self.short_description
+    }
+
+    pub fn detailed_help(&self) -> &Vec<&'a str> {
+        &self.detailed_help
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub text: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Output {
+    pub fn new(text: impl Into<String>) -> Output {
+        Output {
+            text: text.into(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn table(columns: Vec<String>, rows: Vec<Vec<String>>) -> Output {
+        let mut text = columns.join("\t");
+
+        for row in &rows {
+            text.push('\n');
+            text.push_str(&row.join("\t"));
+        }
+
+        Output {
+            text,
+            columns,
+            rows,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CockleError {
+    UnknownCommand(String),
+    HandlerFailed(String),
+}
+
+impl std::fmt::Display for CockleError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CockleError::UnknownCommand(path) => write!(formatter, "no handler registered for '{}'", path),
+            CockleError::HandlerFailed(message) => write!(formatter, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CockleError {}
+
+type Handler = Box<dyn for<'a> Fn(&[ParameterValue<'a>]) -> Result<Output, CockleError>>;
+type PipedHandler = Box<dyn for<'a> Fn(&[ParameterValue<'a>], &Output) -> Result<Output, CockleError>>;
+
+pub struct Runtime {
+    handlers: HashMap<String, Handler>,
+    piped_handlers: HashMap<String, PipedHandler>,
+    help_renderer: HelpRenderer,
+}
+
+impl Runtime {
+    pub fn new() -> Runtime {
+        Runtime {
+            handlers: HashMap::new(),
+            piped_handlers: HashMap::new(),
+            help_renderer: HelpRenderer::new(),
+        }
+    }
+
+    pub fn register(&mut self, path: &str, handler: impl for<'a> Fn(&[ParameterValue<'a>]) -> Result<Output, CockleError> + 'static) {
+        self.handlers.insert(path.to_owned(), Box::new(handler));
+    }
+
+    // Registers a handler that accepts the previous pipeline stage's `Output` as piped input.
+    pub fn register_piped(&mut self, path: &str, handler: impl for<'a> Fn(&[ParameterValue<'a>], &Output) -> Result<Output, CockleError> + 'static) {
+        self.piped_handlers.insert(path.to_owned(), Box::new(handler));
+    }
+
+    pub fn execute(&self, action: Action) -> Result<Output, CockleError> {
+        self.execute_stage(action, None)
+    }
+
+    fn execute_stage(&self, action: Action, piped_input: Option<&Output>) -> Result<Output, CockleError> {
+        match action {
+            Action::Run(path, parameter_values) => {
+                if path == "define" {
+                    return Ok(Output::new(""));
+                }
+
+                match (piped_input, self.piped_handlers.get(&path)) {
+                    (Some(input), Some(handler)) => handler(&parameter_values, input),
+                    _ => match self.handlers.get(&path) {
+                        Some(handler) => handler(&parameter_values),
+                        None => Err(CockleError::UnknownCommand(path)),
+                    },
+                }
+            },
+            Action::Help(path, target) => Ok(Output::new(self.help_renderer.render(&path, &target))),
+            Action::Exit => Ok(Output::new("")),
+            Action::Unknown(name) => Err(CockleError::UnknownCommand(name)),
+            Action::Incorrect(name, _) => Err(CockleError::UnknownCommand(name)),
+            Action::BadParameter(value, _, _) => Err(CockleError::HandlerFailed(format!("bad parameter value '{}'", value))),
+            Action::MissingParameter(long_name, _) => Err(CockleError::HandlerFailed(format!("missing required parameter '{}'", long_name))),
+            Action::ConflictingParameters(first, second, _) => Err(CockleError::HandlerFailed(format!("'{}' conflicts with '{}'", first, second))),
+        }
+    }
+
+    pub fn run_line(&self, parser: &Parser, line: &str) -> Result<Output, CockleError> {
+        self.execute(parser.parse(line.to_owned()))
+    }
+
+    // Runs each `|`-separated stage of the line in order, threading each stage's `Output`
+    // into the next stage's invocation as piped input.
+    pub fn run_pipeline(&self, parser: &Parser, line: &str) -> Result<Output, CockleError> {
+        let mut previous_output: Option<Output> = None;
+
+        for action in parser.parse_pipeline(line.to_owned()) {
+            previous_output = Some(self.execute_stage(action, previous_output.as_ref())?);
+        }
+
+        Ok(previous_output.unwrap_or_else(|| Output::new("")))
+    }
 }
 
-pub struct Runtime {}
+impl Default for Runtime {
+    fn default() -> Runtime {
+        Runtime::new()
+    }
+}
 
+#[derive(Debug)]
 pub struct Parser<'a> {
     verbs: HashMap<String, Verb<'a>>,
+    variables: RefCell<HashMap<String, String>>,
 }
 
 impl<'a> Parser<'a> {
@@ -45,10 +190,32 @@ impl<'a> Parser<'a> {
 
         Parser {
             verbs: verbs_map,
+            variables: RefCell::new(HashMap::new()),
         }
     }
 
     pub fn parse(&self, input: String) -> Action {
+        let trimmed_input = input.trim();
+
+        if trimmed_input == "define" || trimmed_input.starts_with("define ") {
+            return self.define(&trimmed_input["define".len()..]);
+        }
+
+        let input = self.substitute_variables(trimmed_input);
+        let trimmed_input = input.trim();
+
+        if trimmed_input == "help" || trimmed_input.starts_with("help ") {
+            let path: Vec<&str> = trimmed_input.split_whitespace().skip(1).collect();
+
+            return self.resolve_help(&path);
+        }
+
+        if let Some(without_help) = trimmed_input.strip_suffix("--help") {
+            let path: Vec<&str> = without_help.split_whitespace().collect();
+
+            return self.resolve_help(&path);
+        }
+
         let (verb_name, remaining_commands, matching_verb) = match input.split_once(" ") {
             Some((verb_name, remaining_commands)) => {
                 (verb_name.to_owned(), remaining_commands, self.verbs.get(verb_name))
@@ -60,25 +227,118 @@ impl<'a> Parser<'a> {
 
         let action = match matching_verb {
             Some(verb) => {
-                verb.parse(remaining_commands)
+                verb.parse(&verb_name, remaining_commands)
             },
             None => Action::Unknown(verb_name),
         };
 
         action
     }
+
+    // Splits `input` on top-level `|` (a `|` inside a quoted segment is literal), parsing
+    // each stage independently so the `Runtime` can thread stage outputs through the pipeline.
+    pub fn parse_pipeline(&self, input: String) -> Vec<Action> {
+        Self::split_pipeline_stages(&input)
+            .into_iter()
+            .map(|stage| self.parse(stage))
+            .collect()
+    }
+
+    fn split_pipeline_stages(input: &str) -> Vec<String> {
+        let mut stages = Vec::new();
+        let mut current = String::new();
+        let mut in_quotes = false;
+
+        for token in input.chars() {
+            match token {
+                '"' => {
+                    in_quotes = !in_quotes;
+                    current.push(token);
+                },
+                '|' if !in_quotes => {
+                    stages.push(current.trim().to_owned());
+                    current = String::new();
+                },
+                _ => current.push(token),
+            }
+        }
+
+        stages.push(current.trim().to_owned());
+
+        stages
+    }
+
+    fn define(&self, definition: &str) -> Action {
+        match definition.split_once('=') {
+            Some((name, value)) => {
+                self.variables.borrow_mut().insert(name.trim().to_owned(), value.trim().to_owned());
+
+                Action::Run("define".to_owned(), Vec::new())
+            },
+            None => Action::Unknown("define".to_owned()),
+        }
+    }
+
+    fn substitute_variables(&self, input: &str) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.find('[') {
+            match rest[start..].find(']') {
+                Some(offset) => {
+                    let end = start + offset;
+
+                    output.push_str(&rest[..start]);
+
+                    let name = &rest[start + 1..end];
+
+                    match self.variables.borrow().get(name) {
+                        Some(value) => output.push_str(value),
+                        None => {
+                            output.push('[');
+                            output.push_str(name);
+                            output.push(']');
+                        },
+                    }
+
+                    rest = &rest[end + 1..];
+                },
+                None => break,
+            }
+        }
+
+        output.push_str(rest);
+
+        output
+    }
+
+    fn resolve_help(&self, path: &[&str]) -> Action {
+        match path.split_first() {
+            Some((head, rest)) => {
+                match self.verbs.get(*head) {
+                    Some(verb) => {
+                        let (consumed, target) = verb.resolve_help(vec![(*head).to_owned()], rest);
+
+                        Action::Help(consumed.join(" "), target)
+                    },
+                    None => Action::Unknown((*head).to_owned()),
+                }
+            },
+            None => Action::Help(String::new(), HelpTarget::Root(self)),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Verb<'a> {
     name: String,
     verbs: HashMap<String, Verb<'a>>,
-    commands: HashMap<String, Command>,
+    commands: HashMap<String, Command<'a>>,
     manual: Manual<'a>,
 }
 
 impl<'a> Verb<'a> {
-    pub fn new(name: &str, verbs: Option<Vec<Verb<'a>>>, commands: Option<Vec<Command>>, manual: Manual<'a>) -> Verb<'a> {
+    pub fn new(name: &str, verbs: Option<Vec<Verb<'a>>>, commands: Option<Vec<Command<'a>>>, manual: Manual<'a>) -> Verb<'a> {
         let mut verbs_map = HashMap::new();
 
         if let Some(verbs) = verbs {
@@ -108,7 +368,7 @@ impl<'a> Verb<'a> {
         &self.name
     }
 
-    pub fn parse(&self, input: &str) -> Action {
+    pub fn parse(&self, path: &str, input: &str) -> Action {
         let (command_name, remaining_commands) = match input.split_once(" ") {
             Some((command_name, remaining_commands)) => (command_name.to_owned(), remaining_commands),
             None => (input.to_owned(), ""),
@@ -116,17 +376,40 @@ impl<'a> Verb<'a> {
 
         if self.verbs.contains_key(&command_name) {
             let command_verb = self.verbs.get(&command_name).expect(format!("Expected there would be a verb named '{}' but couldn't find it.", command_name).as_str());
+            let child_path = format!("{} {}", path, command_name);
 
-            return command_verb.parse(remaining_commands);
+            return command_verb.parse(&child_path, remaining_commands);
         }
         else if self.commands.contains_key(&command_name) {
             let command = self.commands.get(&command_name).expect(format!("Expected there would be a command named '{}' but couldn't find it.", command_name).as_str());
+            let child_path = format!("{} {}", path, command_name);
 
-            return command.parse(remaining_commands);
+            return command.parse(&child_path, remaining_commands);
         }
 
         Action::Incorrect(input.to_owned(), self)
     }
+
+    fn resolve_help(&self, mut consumed: Vec<String>, path: &[&str]) -> (Vec<String>, HelpTarget) {
+        match path.split_first() {
+            Some((head, rest)) => {
+                if let Some(verb) = self.verbs.get(*head) {
+                    consumed.push((*head).to_owned());
+
+                    verb.resolve_help(consumed, rest)
+                }
+                else if let Some(command) = self.commands.get(*head) {
+                    consumed.push((*head).to_owned());
+
+                    (consumed, HelpTarget::Command(command))
+                }
+                else {
+                    (consumed, HelpTarget::Verb(self))
+                }
+            },
+            None => (consumed, HelpTarget::Verb(self)),
+        }
+    }
 }
 
 impl<'a> Informational for Verb<'a> {
@@ -136,15 +419,17 @@ impl<'a> Informational for Verb<'a> {
 }
 
 #[derive(Debug)]
-pub struct Command {
+pub struct Command<'a> {
     name: String,
     parameters: Vec<Parameter>,
     parameters_by_short_name: HashMap<char, usize>,
     parameters_by_long_name: HashMap<String, usize>,
+    manual: Manual<'a>,
+    groups: Vec<ParamGroup>,
 }
 
-impl Command {
-    pub fn new(name: &str, parameters: Vec<Parameter>) -> Command {
+impl<'a> Command<'a> {
+    pub fn new(name: &str, parameters: Vec<Parameter>, manual: Manual<'a>) -> Command<'a> {
         let parameters_ref = &parameters;
         let parameters_by_short_name = parameters_ref.into_iter().enumerate().map(|(i, x)|(x.short_name, i)).collect::<HashMap<char, usize>>();
         let parameters_by_long_name = parameters_ref.into_iter().enumerate().map(|(i, x)|(x.long_name.clone(), i)).collect::<HashMap<String, usize>>();
@@ -154,87 +439,370 @@ impl Command {
             parameters,
             parameters_by_short_name,
             parameters_by_long_name,
+            manual,
+            groups: Vec::new(),
         }
     }
 
+    // Attaches mutually-exclusive/required parameter groups, enforced at the end of `parse`.
+    pub fn with_groups(mut self, groups: Vec<ParamGroup>) -> Command<'a> {
+        self.groups = groups;
+        self
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
 
-    pub fn parse(&self, parameters: &str) -> Action {
+    pub fn parse(&self, path: &str, parameters: &str) -> Action {
         let tokens = parameters.split_whitespace();
 
-        let mut parameter_values = Vec::new();
+        let mut parameter_values: Vec<ParameterValue> = Vec::new();
+        let mut end_of_options = false;
 
         for token in tokens {
-            if token.starts_with("--") {
-                let parameter_type_token = token.trim_matches('-');
-
-                if let Some(parameter_type_index) = self.parameters_by_long_name.get(parameter_type_token) {
-                    if let Some(parameter_type) = self.parameters.get(*parameter_type_index) {
-                        parameter_values.push(ParameterValue::new(parameter_type))
+            if end_of_options {
+                if let Some(parameter_value) = parameter_values.last_mut() {
+                    if let Err(action) = parameter_value.push_value(token, self) {
+                        return action;
                     }
                 }
+
+                continue;
             }
-            else if token.starts_with("-") {
-                let parameter_type_token = token.trim_matches('-');
 
-                if parameter_type_token.len() > 1 {
-                    return Action::BadParameter(parameter_type_token.to_owned(), self);
+            if token == "--" {
+                end_of_options = true;
+            }
+            else if token.starts_with("--") {
+                let parameter_type_token = token.trim_start_matches('-');
+
+                let (long_name, inline_value) = match parameter_type_token.split_once('=') {
+                    Some((long_name, value)) => (long_name, Some(value)),
+                    None => (parameter_type_token, None),
+                };
+
+                if let Some(parameter_type_index) = self.parameters_by_long_name.get(long_name) {
+                    if let Some(parameter_type) = self.parameters.get(*parameter_type_index) {
+                        parameter_values.push(ParameterValue::new(parameter_type));
+
+                        if let Some(inline_value) = inline_value {
+                            if let Some(parameter_value) = parameter_values.last_mut() {
+                                if let Err(action) = parameter_value.push_value(inline_value, self) {
+                                    return action;
+                                }
+                            }
+                        }
+                    }
                 }
+            }
+            else if token.len() > 1 && token.starts_with("-") {
+                let short_names: Vec<char> = token.trim_start_matches('-').chars().collect();
 
-                if let Some(first_char) = parameter_type_token.chars().next() {
-                    if let Some(parameter_type_index) = self.parameters_by_short_name.get(&first_char) {
+                for (index, short_name) in short_names.iter().enumerate() {
+                    if let Some(parameter_type_index) = self.parameters_by_short_name.get(short_name) {
                         if let Some(parameter_type) = self.parameters.get(*parameter_type_index) {
-                            parameter_values.push(ParameterValue::new(parameter_type))
+                            let is_last = index == short_names.len() - 1;
+
+                            if !is_last && parameter_type.value_kind != ValueKind::Flag {
+                                return Action::BadParameter(short_name.to_string(), parameter_type.value_kind.clone(), self);
+                            }
+
+                            parameter_values.push(ParameterValue::new(parameter_type));
                         }
                     }
                 }
             }
             else {
                 if let Some(parameter_value) = parameter_values.last_mut() {
-                    parameter_value.values.push(token.to_owned());
+                    if let Err(action) = parameter_value.push_value(token, self) {
+                        return action;
+                    }
+                }
+            }
+        }
+
+        for parameter in &self.parameters {
+            if matches!(parameter.arity, Arity::Required) {
+                let satisfied = parameter_values.iter().any(|parameter_value| std::ptr::eq(parameter_value.parameter_type, parameter));
+
+                if !satisfied {
+                    return Action::MissingParameter(parameter.long_name.clone(), self);
+                }
+            }
+        }
+
+        let present_long_names: Vec<&String> = parameter_values.iter().map(|parameter_value| &parameter_value.parameter_type.long_name).collect();
+
+        for parameter_value in &parameter_values {
+            let parameter = parameter_value.parameter_type;
+
+            for conflicting_name in &parameter.conflicts_with {
+                if present_long_names.iter().any(|long_name| *long_name == conflicting_name) {
+                    return Action::ConflictingParameters(parameter.long_name.clone(), conflicting_name.clone(), self);
+                }
+            }
+
+            for required_name in &parameter.requires {
+                if !present_long_names.iter().any(|long_name| *long_name == required_name) {
+                    return Action::MissingParameter(required_name.clone(), self);
+                }
+            }
+        }
+
+        for group in &self.groups {
+            let mut present_members: Vec<&String> = Vec::new();
+
+            for long_name in present_long_names.iter().copied().filter(|long_name| group.members.contains(long_name)) {
+                if !present_members.contains(&long_name) {
+                    present_members.push(long_name);
                 }
             }
+
+            if present_members.len() > 1 {
+                return Action::ConflictingParameters(present_members[0].clone(), present_members[1].clone(), self);
+            }
+
+            if group.required && present_members.is_empty() {
+                return Action::MissingParameter(group.name.clone(), self);
+            }
         }
 
-        Action::Run(parameter_values)
+        Action::Run(path.to_owned(), parameter_values)
+    }
+}
+
+impl<'a> Informational for Command<'a> {
+    fn get_help(&self) -> &Manual {
+        &self.manual
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKind {
+    Flag,
+    String,
+    Integer,
+    Path,
+    Choice(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arity {
+    Optional,
+    Required,
+    Repeated,
+}
+
 #[derive(Debug)]
 pub struct Parameter {
     short_name: char,
     long_name: String,
+    value_kind: ValueKind,
+    arity: Arity,
+    conflicts_with: Vec<String>,
+    requires: Vec<String>,
 }
 
 impl Parameter {
-    pub fn new(short_name: char, long_name: &str) -> Parameter {
+    pub fn new(short_name: char, long_name: &str, value_kind: ValueKind, arity: Arity) -> Parameter {
         Parameter {
             short_name,
             long_name: long_name.to_owned(),
+            value_kind,
+            arity,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+        }
+    }
+
+    // Declares that this parameter cannot appear alongside `long_name`, enforced after parsing.
+    pub fn conflicts_with(mut self, long_name: &str) -> Parameter {
+        self.conflicts_with.push(long_name.to_owned());
+        self
+    }
+
+    // Declares that this parameter cannot appear without `long_name`, enforced after parsing.
+    pub fn requires(mut self, long_name: &str) -> Parameter {
+        self.requires.push(long_name.to_owned());
+        self
+    }
+}
+
+// A named group of a `Command`'s parameters (by long name) of which at most one, or
+// exactly one when `required`, may be present once parsing completes.
+#[derive(Debug)]
+pub struct ParamGroup {
+    name: String,
+    members: Vec<String>,
+    required: bool,
+}
+
+impl ParamGroup {
+    pub fn new(name: &str, members: Vec<&str>, required: bool) -> ParamGroup {
+        ParamGroup {
+            name: name.to_owned(),
+            members: members.into_iter().map(|member| member.to_owned()).collect(),
+            required,
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedValue {
+    Flag(bool),
+    String(String),
+    Integer(i64),
+    Path(PathBuf),
+    Choice(String),
+}
+
 #[derive(Debug)]
 pub struct ParameterValue<'a> {
     pub parameter_type: &'a Parameter,
     pub values: Vec<String>,
+    pub parsed: Vec<ParsedValue>,
 }
 
 impl<'a> ParameterValue<'a> {
     pub fn new(parameter_type: &'a Parameter) -> ParameterValue<'a> {
+        let parsed = match parameter_type.value_kind {
+            ValueKind::Flag => vec![ParsedValue::Flag(true)],
+            _ => Vec::new(),
+        };
+
         ParameterValue {
             parameter_type,
             values: Vec::new(),
+            parsed,
+        }
+    }
+
+    fn push_value<'b>(&mut self, raw_value: &str, command: &'b Command<'b>) -> Result<(), Action<'b>> {
+        if self.parameter_type.value_kind == ValueKind::Flag {
+            return Err(Action::BadParameter(raw_value.to_owned(), ValueKind::Flag, command));
+        }
+
+        if self.parameter_type.arity != Arity::Repeated && !self.values.is_empty() {
+            return Err(Action::BadParameter(raw_value.to_owned(), self.parameter_type.value_kind.clone(), command));
+        }
+
+        let parsed_value = match &self.parameter_type.value_kind {
+            ValueKind::String => ParsedValue::String(raw_value.to_owned()),
+            ValueKind::Path => ParsedValue::Path(PathBuf::from(raw_value)),
+            ValueKind::Integer => match raw_value.parse::<i64>() {
+                Ok(integer_value) => ParsedValue::Integer(integer_value),
+                Err(_) => return Err(Action::BadParameter(raw_value.to_owned(), ValueKind::Integer, command)),
+            },
+            ValueKind::Choice(allowed_values) => {
+                if !allowed_values.iter().any(|allowed_value| allowed_value == raw_value) {
+                    return Err(Action::BadParameter(raw_value.to_owned(), self.parameter_type.value_kind.clone(), command));
+                }
+
+                ParsedValue::Choice(raw_value.to_owned())
+            },
+            ValueKind::Flag => unreachable!(),
+        };
+
+        self.values.push(raw_value.to_owned());
+        self.parsed.push(parsed_value);
+
+        Ok(())
+    }
+}
+
+pub struct HelpRenderer {}
+
+impl HelpRenderer {
+    pub fn new() -> HelpRenderer {
+        HelpRenderer {}
+    }
+
+    pub fn render(&self, path: &str, target: &HelpTarget) -> String {
+        match target {
+            HelpTarget::Root(parser) => self.render_root(parser),
+            HelpTarget::Verb(verb) => self.render_verb(path, verb),
+            HelpTarget::Command(command) => self.render_command(path, command),
+        }
+    }
+
+    fn render_root(&self, parser: &Parser) -> String {
+        let mut verb_names: Vec<&String> = parser.verbs.keys().collect();
+        verb_names.sort();
+
+        let mut output = String::from("Usage: <verb> ...\n\nVerbs:\n");
+
+        for verb_name in verb_names {
+            let verb = &parser.verbs[verb_name];
+
+            output.push_str(&format!("  {:<16}{}\n", verb_name, verb.get_help().short_description()));
+        }
+
+        output
+    }
+
+    fn render_verb(&self, path: &str, verb: &Verb) -> String {
+        let mut output = format!("{}\n\nUsage: {} <subcommand> [parameters]\n", verb.get_help().short_description(), path);
+
+        let mut verb_names: Vec<&String> = verb.verbs.keys().collect();
+        verb_names.sort();
+
+        let mut command_names: Vec<&String> = verb.commands.keys().collect();
+        command_names.sort();
+
+        if !verb_names.is_empty() || !command_names.is_empty() {
+            output.push_str("\nSubcommands:\n");
+
+            for verb_name in verb_names {
+                let child_verb = &verb.verbs[verb_name];
+
+                output.push_str(&format!("  {:<16}{}\n", verb_name, child_verb.get_help().short_description()));
+            }
+
+            for command_name in command_names {
+                let command = &verb.commands[command_name];
+
+                output.push_str(&format!("  {:<16}{}\n", command_name, command.get_help().short_description()));
+            }
+        }
+
+        output
+    }
+
+    fn render_command(&self, path: &str, command: &Command) -> String {
+        let usage = command.parameters.iter().map(Self::parameter_usage).collect::<Vec<String>>().join(" ");
+
+        format!("{}\n\nUsage: {} {}\n", command.get_help().short_description(), path, usage)
+    }
+
+    fn parameter_usage(parameter: &Parameter) -> String {
+        let value_hint = match &parameter.value_kind {
+            ValueKind::Flag => String::new(),
+            ValueKind::String => " <value>".to_owned(),
+            ValueKind::Integer => " <integer>".to_owned(),
+            ValueKind::Path => " <path>".to_owned(),
+            ValueKind::Choice(allowed_values) => format!(" <{}>", allowed_values.join("|")),
+        };
+
+        let flag = format!("-{}/--{}{}", parameter.short_name, parameter.long_name, value_hint);
+
+        match parameter.arity {
+            Arity::Required => flag,
+            Arity::Optional => format!("[{}]", flag),
+            Arity::Repeated => format!("[{}...]", flag),
         }
     }
 }
 
+impl Default for HelpRenderer {
+    fn default() -> HelpRenderer {
+        HelpRenderer::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{Action, Command, Manual, Parameter, Parser, Verb};
+    use crate::{Action, Arity, CockleError, Command, HelpRenderer, HelpTarget, Manual, Output, ParamGroup, Parameter, ParsedValue, Parser, Runtime, ValueKind, Verb};
 
     #[test]
     fn parse_command_with_one_parameter_short_name() {
@@ -247,8 +815,14 @@ mod tests {
                         Command::new(
                             "table",
                             vec![
-                                Parameter::new('i', "name"),
+                                Parameter::new('i', "name", ValueKind::String, Arity::Optional),
                             ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
                         ),
                     ],
                 ),
@@ -263,10 +837,11 @@ mod tests {
 
         let action = parser.parse("list table -i my_table_name".to_string());
 
-        if let Action::Run(parameter_value) = action {
+        if let Action::Run(_, parameter_value) = action {
             assert_eq!('i', parameter_value.get(0).unwrap().parameter_type.short_name);
             assert_eq!("name", parameter_value.get(0).unwrap().parameter_type.long_name);
             assert_eq!("my_table_name", parameter_value.get(0).unwrap().values.get(0).unwrap());
+            assert_eq!(&ParsedValue::String("my_table_name".to_owned()), parameter_value.get(0).unwrap().parsed.get(0).unwrap());
         }
         else {
             assert!(false);
@@ -284,9 +859,15 @@ mod tests {
                         Command::new(
                             "table",
                             vec![
-                                Parameter::new('i', "name"),
-                                Parameter::new('n', "count"),
+                                Parameter::new('i', "name", ValueKind::String, Arity::Optional),
+                                Parameter::new('n', "count", ValueKind::Integer, Arity::Optional),
                             ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
                         ),
                     ],
                 ),
@@ -300,8 +881,8 @@ mod tests {
         ]);
 
         let action = parser.parse("list table -i my_table_name -n 10".to_string());
-        
-        if let Action::Run(parameter_value) = action {
+
+        if let Action::Run(_, parameter_value) = action {
             assert_eq!('i', parameter_value.get(0).unwrap().parameter_type.short_name);
             assert_eq!("name", parameter_value.get(0).unwrap().parameter_type.long_name);
             assert_eq!("my_table_name", parameter_value.get(0).unwrap().values.get(0).unwrap());
@@ -309,6 +890,743 @@ mod tests {
             assert_eq!('n', parameter_value.get(1).unwrap().parameter_type.short_name);
             assert_eq!("count", parameter_value.get(1).unwrap().parameter_type.long_name);
             assert_eq!("10", parameter_value.get(1).unwrap().values.get(0).unwrap());
+            assert_eq!(&ParsedValue::Integer(10), parameter_value.get(1).unwrap().parsed.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_invalid_integer_value_is_bad_parameter() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('n', "count", ValueKind::Integer, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -n not_a_number".to_string());
+
+        if let Action::BadParameter(value, value_kind, _) = action {
+            assert_eq!("not_a_number", value);
+            assert_eq!(ValueKind::Integer, value_kind);
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_missing_required_parameter() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('i', "name", ValueKind::String, Arity::Required),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table".to_string());
+
+        if let Action::MissingParameter(long_name, _) = action {
+            assert_eq!("name", long_name);
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_repeated_parameter_accumulates_values() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('i', "name", ValueKind::String, Arity::Repeated),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -i first second".to_string());
+
+        if let Action::Run(_, parameter_value) = action {
+            assert_eq!("first", parameter_value.get(0).unwrap().values.get(0).unwrap());
+            assert_eq!("second", parameter_value.get(0).unwrap().values.get(1).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_long_name_equals_value() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('i', "name", ValueKind::String, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table --name=my_table_name".to_string());
+
+        if let Action::Run(_, parameter_value) = action {
+            assert_eq!("my_table_name", parameter_value.get(0).unwrap().values.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_bundled_short_flags() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('a', "all", ValueKind::Flag, Arity::Optional),
+                                Parameter::new('l', "long", ValueKind::Flag, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -al".to_string());
+
+        if let Action::Run(_, parameter_value) = action {
+            assert_eq!('a', parameter_value.get(0).unwrap().parameter_type.short_name);
+            assert_eq!('l', parameter_value.get(1).unwrap().parameter_type.short_name);
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_non_final_bundled_flag_expecting_value_is_bad_parameter() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('i', "name", ValueKind::String, Arity::Optional),
+                                Parameter::new('l', "long", ValueKind::Flag, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -il my_table_name".to_string());
+
+        if let Action::BadParameter(value, value_kind, _) = action {
+            assert_eq!("i", value);
+            assert_eq!(ValueKind::String, value_kind);
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_end_of_options_terminator_treats_dashed_tokens_as_values() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('i', "name", ValueKind::String, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -i -- -not-a-flag".to_string());
+
+        if let Action::Run(_, parameter_value) = action {
+            assert_eq!("-not-a-flag", parameter_value.get(0).unwrap().values.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    fn test_parser() -> Parser<'static> {
+        Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('i', "name", ValueKind::String, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ])
+    }
+
+    #[test]
+    fn parse_help_command_resolves_to_verb() {
+        let parser = test_parser();
+
+        let action = parser.parse("help list".to_string());
+
+        if let Action::Help(path, HelpTarget::Verb(verb)) = action {
+            assert_eq!("list", path);
+            assert_eq!("list", verb.name());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_help_command_resolves_to_command() {
+        let parser = test_parser();
+
+        let action = parser.parse("help list table".to_string());
+
+        if let Action::Help(path, HelpTarget::Command(command)) = action {
+            assert_eq!("list table", path);
+            assert_eq!("table", command.name());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_trailing_help_flag_resolves_to_command() {
+        let parser = test_parser();
+
+        let action = parser.parse("list table --help".to_string());
+
+        if let Action::Help(path, HelpTarget::Command(command)) = action {
+            assert_eq!("list table", path);
+            assert_eq!("table", command.name());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn help_renderer_renders_command_usage() {
+        let parser = test_parser();
+        let renderer = HelpRenderer::new();
+
+        let action = parser.parse("help list table".to_string());
+
+        if let Action::Help(path, target) = action {
+            let rendered = renderer.render(&path, &target);
+
+            assert!(rendered.contains("list tables"));
+            assert!(rendered.contains("Usage: list table"));
+            assert!(rendered.contains("-i/--name <value>"));
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn runtime_dispatches_run_action_to_registered_handler() {
+        let parser = test_parser();
+        let mut runtime = Runtime::new();
+
+        runtime.register("list table", |parameter_values| {
+            let name = parameter_values.get(0).map(|parameter_value| parameter_value.values.get(0).unwrap().clone()).unwrap_or_default();
+
+            Ok(Output::new(format!("listed {}", name)))
+        });
+
+        let output = runtime.run_line(&parser, "list table -i my_table_name").unwrap();
+
+        assert_eq!("listed my_table_name", output.text);
+    }
+
+    #[test]
+    fn runtime_errors_on_run_action_with_no_registered_handler() {
+        let parser = test_parser();
+        let runtime = Runtime::new();
+
+        let result = runtime.run_line(&parser, "list table -i my_table_name");
+
+        assert!(matches!(result, Err(CockleError::UnknownCommand(path)) if path == "list table"));
+    }
+
+    #[test]
+    fn runtime_dispatches_help_action_to_the_help_renderer() {
+        let parser = test_parser();
+        let runtime = Runtime::new();
+
+        let output = runtime.run_line(&parser, "help list table").unwrap();
+
+        assert!(output.text.contains("Usage: list table"));
+    }
+
+    #[test]
+    fn define_records_a_variable_and_interpolation_substitutes_it() {
+        let parser = test_parser();
+
+        parser.parse("define tbl = my_table_name".to_string());
+
+        let action = parser.parse("list table -i [tbl]".to_string());
+
+        if let Action::Run(_, parameter_value) = action {
+            assert_eq!("my_table_name", parameter_value.get(0).unwrap().values.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn interpolation_leaves_unknown_variable_names_untouched() {
+        let parser = test_parser();
+
+        let action = parser.parse("list table -i [missing]".to_string());
+
+        if let Action::Run(_, parameter_value) = action {
+            assert_eq!("[missing]", parameter_value.get(0).unwrap().values.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_pipeline_splits_into_one_action_per_stage() {
+        let parser = test_parser();
+
+        let actions = parser.parse_pipeline("list table -i first | list table -i second".to_string());
+
+        assert_eq!(2, actions.len());
+
+        if let Action::Run(_, parameter_value) = &actions[0] {
+            assert_eq!("first", parameter_value.get(0).unwrap().values.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+
+        if let Action::Run(_, parameter_value) = &actions[1] {
+            assert_eq!("second", parameter_value.get(0).unwrap().values.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_pipeline_treats_a_quoted_bar_as_literal() {
+        let parser = test_parser();
+
+        let actions = parser.parse_pipeline("list table -i \"a|b\"".to_string());
+
+        assert_eq!(1, actions.len());
+
+        if let Action::Run(_, parameter_value) = &actions[0] {
+            assert_eq!("\"a|b\"", parameter_value.get(0).unwrap().values.get(0).unwrap());
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn runtime_threads_piped_output_into_the_next_stage() {
+        let parser = test_parser();
+        let mut runtime = Runtime::new();
+
+        runtime.register("list table", |parameter_values| {
+            let name = parameter_values.get(0).map(|parameter_value| parameter_value.values.get(0).unwrap().clone()).unwrap_or_default();
+
+            Ok(Output::table(vec!["name".to_owned()], vec![vec![name]]))
+        });
+
+        runtime.register_piped("list table", |_, piped_input| {
+            Ok(Output::new(format!("received {} rows", piped_input.rows.len())))
+        });
+
+        let output = runtime.run_pipeline(&parser, "list table -i first | list table -i second").unwrap();
+
+        assert_eq!("received 1 rows", output.text);
+    }
+
+    #[test]
+    fn runtime_falls_back_to_the_unpiped_handler_when_none_is_registered_for_piped_input() {
+        let parser = test_parser();
+        let mut runtime = Runtime::new();
+
+        runtime.register("list table", |parameter_values| {
+            let name = parameter_values.get(0).map(|parameter_value| parameter_value.values.get(0).unwrap().clone()).unwrap_or_default();
+
+            Ok(Output::new(format!("listed {}", name)))
+        });
+
+        let output = runtime.run_pipeline(&parser, "list table -i first | list table -i second").unwrap();
+
+        assert_eq!("listed second", output.text);
+    }
+
+    #[test]
+    fn parse_command_with_conflicting_parameters_is_conflicting_parameters() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('j', "json", ValueKind::Flag, Arity::Optional).conflicts_with("csv"),
+                                Parameter::new('c', "csv", ValueKind::Flag, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -j -c".to_string());
+
+        if let Action::ConflictingParameters(first, second, _) = action {
+            assert_eq!("json", first);
+            assert_eq!("csv", second);
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_unsatisfied_requires_is_missing_parameter() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('o', "output", ValueKind::String, Arity::Optional).requires("format"),
+                                Parameter::new('f', "format", ValueKind::String, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -o out.txt".to_string());
+
+        if let Action::MissingParameter(long_name, _) = action {
+            assert_eq!("format", long_name);
+        }
+        else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn parse_command_with_mutually_exclusive_group_allows_exactly_one_member() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('j', "json", ValueKind::Flag, Arity::Optional),
+                                Parameter::new('c', "csv", ValueKind::Flag, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ).with_groups(vec![
+                            ParamGroup::new("output-format", vec!["json", "csv"], true),
+                        ]),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -j".to_string());
+
+        assert!(matches!(action, Action::Run(_, _)));
+    }
+
+    #[test]
+    fn parse_command_with_a_group_member_repeated_is_not_a_conflict() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('j', "json", ValueKind::Flag, Arity::Optional),
+                                Parameter::new('c', "csv", ValueKind::Flag, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ).with_groups(vec![
+                            ParamGroup::new("output-format", vec!["json", "csv"], true),
+                        ]),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table -j -j".to_string());
+
+        assert!(matches!(action, Action::Run(_, _)));
+    }
+
+    #[test]
+    fn parse_command_with_empty_required_group_is_missing_parameter() {
+        let parser = Parser::new(vec![
+            Verb::new(
+                "list",
+                None,
+                Some(
+                    vec![
+                        Command::new(
+                            "table",
+                            vec![
+                                Parameter::new('j', "json", ValueKind::Flag, Arity::Optional),
+                                Parameter::new('c', "csv", ValueKind::Flag, Arity::Optional),
+                            ],
+                            Manual::new(
+                                "list tables",
+                                vec![
+                                    "",
+                                ]
+                            ),
+                        ).with_groups(vec![
+                            ParamGroup::new("output-format", vec!["json", "csv"], true),
+                        ]),
+                    ],
+                ),
+                Manual::new(
+                    "list all the elements",
+                    vec![
+                        "",
+                    ]
+                )
+            ),
+        ]);
+
+        let action = parser.parse("list table".to_string());
+
+        if let Action::MissingParameter(long_name, _) = action {
+            assert_eq!("output-format", long_name);
         }
         else {
             assert!(false);